@@ -0,0 +1,53 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Position {
+    pub x: u8,
+    pub y: u8,
+    pub z: u8,
+}
+
+pub struct Renderable {
+    path: String,
+}
+
+impl Renderable {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+pub struct Wall {}
+
+pub struct Player {}
+
+/// Marks an entity as part of a push chain: it can be shifted by the
+/// player (or by another `Movable` being pushed) as long as the cell it
+/// would move into is empty.
+pub struct Movable {}
+
+/// Marks an entity that can never be pushed; a push chain that reaches
+/// one is cancelled entirely.
+pub struct Immovable {}
+
+pub struct Box {
+    pub colour: BoxColour,
+}
+
+pub struct BoxSpot {
+    pub colour: BoxColour,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BoxColour {
+    Red,
+    Blue,
+}
+
+pub fn matches_box_colour(box_colour: &BoxColour, box_spot_colour: &BoxColour) -> bool {
+    box_colour == box_spot_colour
+}
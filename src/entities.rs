@@ -0,0 +1,95 @@
+use crate::components::*;
+
+pub fn create_wall(world: &mut legion::World, position: Position) {
+    world.push((
+        Position { z: 10, ..position },
+        Renderable::new("/images/wall.png"),
+        Wall {},
+        Immovable {},
+    ));
+}
+
+pub fn create_floor(world: &mut legion::World, position: Position) {
+    world.push((
+        Position { z: 5, ..position },
+        Renderable::new("/images/floor.png"),
+    ));
+}
+
+pub fn create_box(world: &mut legion::World, position: Position, colour: BoxColour) {
+    let path = match colour {
+        BoxColour::Red => "/images/box_red_1.png",
+        BoxColour::Blue => "/images/box_blue_1.png",
+    };
+    world.push((
+        Position { z: 10, ..position },
+        Renderable::new(path),
+        Box { colour },
+        Movable {},
+    ));
+}
+
+pub fn create_box_spot(world: &mut legion::World, position: Position, colour: BoxColour) {
+    let path = match colour {
+        BoxColour::Red => "/images/box_spot_red.png",
+        BoxColour::Blue => "/images/box_spot_blue.png",
+    };
+    world.push((
+        Position { z: 9, ..position },
+        Renderable::new(path),
+        BoxSpot { colour },
+    ));
+}
+
+pub fn create_player(world: &mut legion::World, position: Position) {
+    world.push((
+        Position { z: 10, ..position },
+        Renderable::new("/images/player_1.png"),
+        Player {},
+        Movable {},
+    ));
+}
+
+/// Reads the `(Position, &str)` tokens produced by `game::parse_map` and
+/// spawns the matching entities into `world`. Each cell is one of:
+/// `.` floor, `W` wall, `P` player (on floor), `B`/`BB` box (red/blue),
+/// `S`/`SS` box spot (red/blue), `N` empty space (nothing spawned).
+pub fn create_entities_from_map(
+    world: &mut legion::World,
+    map: Vec<(Position, &str)>,
+) -> ggez::GameResult {
+    for (position, token) in map.into_iter() {
+        match token {
+            "." => create_floor(world, position),
+            "W" => create_wall(world, position),
+            "P" => {
+                create_floor(world, position);
+                create_player(world, position);
+            }
+            "B" => {
+                create_floor(world, position);
+                create_box(world, position, BoxColour::Red);
+            }
+            "BB" => {
+                create_floor(world, position);
+                create_box(world, position, BoxColour::Blue);
+            }
+            "S" => {
+                create_floor(world, position);
+                create_box_spot(world, position, BoxColour::Red);
+            }
+            "SS" => {
+                create_floor(world, position);
+                create_box_spot(world, position, BoxColour::Blue);
+            }
+            "N" => (),
+            c => {
+                return Err(ggez::GameError::ResourceLoadError(format!(
+                    "unrecognized map token '{}'",
+                    c
+                )))
+            }
+        }
+    }
+    Ok(())
+}
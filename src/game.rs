@@ -1,30 +1,34 @@
 use ggez::event;
+use ggez::filesystem;
 use ggez::graphics;
 use ggez::input::keyboard;
 use ggez::timer;
+use std::io::Read;
 
 use crate::components;
 use crate::entities;
 use crate::resources;
 use crate::systems;
 
-pub const MAP_WIDTH: u8 = 9;
-pub const MAP_HEIGHT: u8 = 9;
-
 pub const TILE_WIDTH: f32 = 48.0;
 pub const TILE_HEIGHT: f32 = 48.0;
 
 pub const ARENA_WIDTH: f32 = 720.0;
-pub const ARENA_HEIGHT: f32 = MAP_HEIGHT as f32 * TILE_HEIGHT;
+pub const ARENA_HEIGHT: f32 = 9.0 * TILE_HEIGHT;
 
 const FPS: u32 = 60;
 
+const MAPS_DIR: &str = "/maps";
+
 const SOUNDS: &[&str] = &[
     "/sounds/wall.wav",
     "/sounds/correct.wav",
     "/sounds/incorrect.wav",
+    "/sounds/background.wav",
 ];
 
+const MUSIC: &str = "/sounds/background.wav";
+
 const IMAGES: &[&str] = &[
     "/images/box_blue_1.png",
     "/images/box_blue_2.png",
@@ -43,29 +47,39 @@ pub struct Game {
     world: legion::World,
     resources: legion::Resources,
     schedule: legion::Schedule,
+    /// Set when the current level's map file failed to parse, so `draw`
+    /// can show it instead of the (missing) gameplay.
+    level_error: Option<String>,
 }
 
 impl Game {
-    pub fn new(ctx: &mut ggez::Context, map_str: &str) -> ggez::GameResult<Self> {
+    pub fn new(ctx: &mut ggez::Context) -> ggez::GameResult<Self> {
         // Load game's images into memory.
         let mut audio_store = resources::AudioStore::default();
         load_sounds(ctx, &mut audio_store, SOUNDS)?;
+        audio_store.play_music(MUSIC)?;
 
         // Load game's sound effects into memory.
         let mut drawable_store = resources::DrawableStore::default();
         load_images(ctx, &mut drawable_store, IMAGES)?;
 
-        // Load game's map and create the entity as specified by the map.
-        let mut world = legion::World::default();
-        let map = parse_map(map_str);
-        entities::create_entities_from_map(&mut world, map)?;
-
         // Initialize shared resources.
         let mut resources = legion::Resources::default();
         resources.insert(resources::Time::default());
         resources.insert(resources::GamePlay::default());
         resources.insert(resources::KeyPressedEventQueue::default());
         resources.insert(resources::GamePlayEventQueue::default());
+        resources.insert(resources::MoveHistory::default());
+
+        // Discover the ordered set of levels under `/maps` and load the
+        // first one.
+        let level_set = resources::LevelSet::new(discover_level_paths(ctx)?);
+        let mut world = legion::World::default();
+        let level_error = match level_set.current_path() {
+            Ok(path) => load_level(ctx, &mut world, &mut resources, path).err(),
+            Err(e) => Some(e),
+        };
+        resources.insert(level_set);
         resources.insert(audio_store);
         resources.insert(drawable_store);
 
@@ -79,8 +93,34 @@ impl Game {
             world,
             resources,
             schedule,
+            level_error,
         })
     }
+
+    /// Tears down the current level's entities and resets the per-level
+    /// resources (`Time`, `GamePlay`, the event queues) before rebuilding
+    /// from `level_set`'s current map. Called whenever `update` sees a
+    /// `Won` transition on a level that is not the last one, and by the
+    /// `R` key to restart the current level.
+    fn load_current_level(&mut self, ctx: &mut ggez::Context) {
+        self.world = legion::World::default();
+        self.resources.insert(resources::Time::default());
+        self.resources.insert(resources::GamePlay::default());
+        self.resources.insert(resources::KeyPressedEventQueue::default());
+        self.resources.insert(resources::GamePlayEventQueue::default());
+        self.resources.insert(resources::MoveHistory::default());
+
+        let current_path = self
+            .resources
+            .get::<resources::LevelSet>()
+            .unwrap()
+            .current_path()
+            .map(|path| path.to_string());
+        self.level_error = match current_path {
+            Ok(path) => load_level(ctx, &mut self.world, &mut self.resources, &path).err(),
+            Err(e) => Some(e),
+        };
+    }
 }
 
 impl event::EventHandler for Game {
@@ -90,14 +130,39 @@ impl event::EventHandler for Game {
                 time.alive += timer::delta(ctx);
             }
             self.schedule.execute(&mut self.world, &mut self.resources);
+
+            let won = self
+                .resources
+                .get::<resources::GamePlay>()
+                .map_or(false, |game_play| {
+                    game_play.state == resources::GameplayState::Won
+                });
+            if won {
+                let advanced = self
+                    .resources
+                    .get_mut::<resources::LevelSet>()
+                    .unwrap()
+                    .advance();
+                if advanced {
+                    self.load_current_level(ctx);
+                }
+                // If this was the last level, leave `GamePlay::state` as
+                // `Won` so `render_gameplay_data` keeps showing the
+                // completion screen.
+            }
         }
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
         graphics::clear(ctx, graphics::WHITE);
-        systems::render_entities(ctx, &self.world, &self.resources)?;
-        systems::render_gameplay_data(ctx, &self.resources)?;
+        if let Some(message) = &self.level_error {
+            let text = graphics::Text::new(format!("Failed to load level: {}", message));
+            graphics::draw(ctx, &text, graphics::DrawParam::new())?;
+        } else {
+            systems::render_entities(ctx, &self.world, &self.resources)?;
+            systems::render_gameplay_data(ctx, &self.resources)?;
+        }
         graphics::present(ctx)
     }
 
@@ -108,14 +173,22 @@ impl event::EventHandler for Game {
         _keymods: keyboard::KeyMods,
         _repeat: bool,
     ) {
-        if keycode == keyboard::KeyCode::Escape {
-            event::quit(ctx);
+        match keycode {
+            keyboard::KeyCode::Escape => event::quit(ctx),
+            keyboard::KeyCode::U => {
+                let mut move_history = self.resources.get_mut::<resources::MoveHistory>().unwrap();
+                let mut game_play = self.resources.get_mut::<resources::GamePlay>().unwrap();
+                systems::undo_last_move(&mut self.world, &mut *move_history, &mut *game_play);
+            }
+            keyboard::KeyCode::R => self.load_current_level(ctx),
+            _ => {
+                let key_pressed_events =
+                    self.resources.get_mut::<resources::KeyPressedEventQueue>();
+                if let Some(mut key_pressed_events) = key_pressed_events {
+                    key_pressed_events.queue.push(keycode);
+                };
+            }
         }
-
-        let key_pressed_events = self.resources.get_mut::<resources::KeyPressedEventQueue>();
-        if let Some(mut key_pressed_events) = key_pressed_events {
-            key_pressed_events.queue.push(keycode);
-        };
     }
 }
 
@@ -141,9 +214,53 @@ fn load_images(
     Ok(())
 }
 
+/// Lists the level map files under `MAPS_DIR`, sorted by name (`01.txt`,
+/// `02.txt`, ...) so level order matches file order. Only paths with a
+/// `.txt` extension are considered, so stray non-map files under `MAPS_DIR` don't get
+/// handed to `load_level`.
+fn discover_level_paths(ctx: &mut ggez::Context) -> ggez::GameResult<Vec<String>> {
+    let mut paths: Vec<String> = filesystem::read_dir(ctx, MAPS_DIR)?
+        .filter(|path| path.extension().map_or(false, |ext| ext == "txt"))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Reads and parses the map file at `path`, records its true dimensions
+/// in `resources::MapDimensions`, then spawns its entities into `world`.
+/// Returns the underlying error instead of panicking so the caller can
+/// show it on screen rather than crash the event loop.
+fn load_level(
+    ctx: &mut ggez::Context,
+    world: &mut legion::World,
+    resources: &mut legion::Resources,
+    path: &str,
+) -> ggez::GameResult {
+    let mut file = filesystem::open(ctx, path)?;
+    let mut map_str = String::new();
+    file.read_to_string(&mut map_str)
+        .map_err(|e| ggez::GameError::ResourceLoadError(e.to_string()))?;
+
+    let map = parse_map(&map_str);
+    resources.insert(map_dimensions(&map));
+    entities::create_entities_from_map(world, map)
+}
+
+/// Computes the bounding box of a parsed map: rows may have irregular
+/// lengths (file-loaded maps, unlike the original hardcoded one, aren't
+/// guaranteed to be square), so width is the widest row rather than an
+/// assumed constant.
+fn map_dimensions(map: &[(components::Position, &str)]) -> resources::MapDimensions {
+    let width = map.iter().map(|(position, _)| position.x).max().unwrap_or(0) + 1;
+    let height = map.iter().map(|(position, _)| position.y).max().unwrap_or(0) + 1;
+    resources::MapDimensions { width, height }
+}
+
 fn parse_map(map_str: &str) -> Vec<(components::Position, &str)> {
     map_str
         .split('\n')
+        .filter(|row| !row.trim().is_empty())
         .enumerate()
         .flat_map(|(y, row)| {
             row.trim().split(' ').enumerate().map(move |(x, val)| {
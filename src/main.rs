@@ -0,0 +1,24 @@
+mod components;
+mod entities;
+mod game;
+mod resources;
+mod systems;
+
+use ggez::conf;
+use ggez::event;
+use ggez::{ContextBuilder, GameResult};
+use std::path;
+
+fn main() -> GameResult {
+    let resource_dir = path::PathBuf::from("./resources");
+
+    let context_builder = ContextBuilder::new("sokoban", "sokoban-rs")
+        .window_setup(conf::WindowSetup::default().title("Sokoban!"))
+        .window_mode(conf::WindowMode::default().dimensions(game::ARENA_WIDTH, game::ARENA_HEIGHT))
+        .add_resource_path(resource_dir);
+
+    let (mut context, mut event_loop) = context_builder.build()?;
+
+    let game = game::Game::new(&mut context)?;
+    event::run(&mut context, &mut event_loop, game)
+}
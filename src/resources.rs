@@ -0,0 +1,185 @@
+use ggez::audio::{self, SoundSource};
+use ggez::graphics::{FilterMode, Image};
+use ggez::input::keyboard::KeyCode;
+use ggez::{Context, GameResult};
+use legion::Entity;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::components::Position;
+
+/// Sources play a little flat and mechanical if triggered with the
+/// exact same pitch every time; nudge each play within this range.
+const SFX_PITCH_VARIATION: (f32, f32) = (0.9, 1.1);
+
+#[derive(Default)]
+pub struct Time {
+    pub alive: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameplayState {
+    Playing,
+    Won,
+}
+
+impl Default for GameplayState {
+    fn default() -> Self {
+        Self::Playing
+    }
+}
+
+#[derive(Default)]
+pub struct GamePlay {
+    pub state: GameplayState,
+    pub moves_count: u32,
+}
+
+/// The positions an `input_handling_system` move displaced, recorded
+/// before the move was applied so it can be undone.
+pub struct MoveRecord {
+    pub changes: Vec<(Entity, Position)>,
+}
+
+/// Stack of `MoveRecord`s, one per committed move, in the order they
+/// happened. `U` pops the most recent one and restores its positions.
+#[derive(Default)]
+pub struct MoveHistory {
+    pub records: Vec<MoveRecord>,
+}
+
+#[derive(Default)]
+pub struct KeyPressedEventQueue {
+    pub queue: Vec<KeyCode>,
+}
+
+pub enum GameplayEvent {
+    PlayerHitObstacle,
+    BoxPlacedOnSpot(bool),
+}
+
+#[derive(Default)]
+pub struct GamePlayEventQueue {
+    pub queue: Vec<GameplayEvent>,
+}
+
+#[derive(Default)]
+pub struct AudioStore {
+    sounds: HashMap<String, audio::Source>,
+}
+
+impl AudioStore {
+    pub fn add_sound(&mut self, ctx: &mut Context, sound_path: &str) -> GameResult {
+        let sound = audio::Source::new(ctx, sound_path)?;
+        self.sounds.insert(sound_path.to_string(), sound);
+        Ok(())
+    }
+
+    /// Starts `sound_path` looping as background music. Intended to be
+    /// called once, e.g. from `Game::new`.
+    pub fn play_music(&mut self, sound_path: &str) -> GameResult {
+        let sound = self.sound_mut(sound_path);
+        sound.set_repeat(true);
+        sound.play()
+    }
+
+    /// Plays `sound_path` with a small random pitch offset, and an
+    /// optional fade-in, so repeated gameplay stings (wall bump, correct
+    /// / incorrect placement) don't sound mechanically identical. Plays
+    /// a clone of the cached source rather than the cached instance
+    /// itself, so the pitch/fade-in here don't leak onto later plays of
+    /// the same sound and overlapping triggers don't fight over one
+    /// `Source`.
+    pub fn play_sfx_varied(&mut self, sound_path: &str, fade_in: Option<Duration>) -> GameResult {
+        let (low, high) = SFX_PITCH_VARIATION;
+        let pitch = rand::thread_rng().gen_range(low, high);
+        let mut sound = self.sound_mut(sound_path).clone();
+        sound.set_pitch(pitch);
+        if let Some(duration) = fade_in {
+            sound.set_fade_in(duration);
+        }
+        sound.play_detached()
+    }
+
+    fn sound_mut(&mut self, sound_path: &str) -> &mut audio::Source {
+        self.sounds
+            .get_mut(sound_path)
+            .unwrap_or_else(|| panic!("Expected sound: {}", sound_path))
+    }
+}
+
+#[derive(Default)]
+pub struct DrawableStore {
+    images: HashMap<String, Image>,
+}
+
+impl DrawableStore {
+    pub fn add_image(
+        &mut self,
+        ctx: &mut Context,
+        image_path: &str,
+        filter_mode: FilterMode,
+    ) -> GameResult {
+        let mut image = Image::new(ctx, image_path)?;
+        image.set_filter(filter_mode);
+        self.images.insert(image_path.to_string(), image);
+        Ok(())
+    }
+
+    pub fn image(&self, image_path: &str) -> &Image {
+        self.images
+            .get(image_path)
+            .unwrap_or_else(|| panic!("Expected image: {}", image_path))
+    }
+}
+
+/// The true bounds of the currently loaded map, computed from its parsed
+/// tiles rather than assumed to be the fixed 9x9 grid. Lets
+/// `systems::render_entities` / `render_gameplay_data` center and scale
+/// levels of any size to fit the window.
+pub struct MapDimensions {
+    pub width: u8,
+    pub height: u8,
+}
+
+/// Tracks the ordered set of level map files the game cycles through and
+/// which one is currently loaded. The maps are read lazily by
+/// `game::Game` from `LevelSet::current_path` whenever a level needs to
+/// be (re)built.
+pub struct LevelSet {
+    paths: Vec<String>,
+    current: usize,
+}
+
+impl LevelSet {
+    pub fn new(paths: Vec<String>) -> Self {
+        Self { paths, current: 0 }
+    }
+
+    /// Errors if no level paths were discovered, instead of indexing
+    /// blindly into an empty `paths` and panicking before the game ever
+    /// gets a chance to show it on screen.
+    pub fn current_path(&self) -> GameResult<&str> {
+        self.paths
+            .get(self.current)
+            .map(String::as_str)
+            .ok_or_else(|| {
+                ggez::GameError::ResourceLoadError("no level maps found under /maps".to_string())
+            })
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.current + 1 >= self.paths.len()
+    }
+
+    /// Advances to the next level. Returns `false` (and leaves the index
+    /// unchanged) if the current level was already the last one.
+    pub fn advance(&mut self) -> bool {
+        if self.is_last() {
+            return false;
+        }
+        self.current += 1;
+        true
+    }
+}
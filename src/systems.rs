@@ -0,0 +1,384 @@
+use ggez::graphics::{self, DrawParam, Text};
+use ggez::input::keyboard::KeyCode;
+use ggez::nalgebra as na;
+use ggez::{Context, GameResult};
+use legion::world::SubWorld;
+use legion::*;
+use std::time::Duration;
+
+use crate::components::*;
+use crate::game::{TILE_HEIGHT, TILE_WIDTH};
+use crate::resources::*;
+
+#[system]
+#[read_component(Position)]
+#[write_component(Position)]
+#[read_component(Player)]
+#[read_component(Box)]
+#[read_component(BoxSpot)]
+#[read_component(Movable)]
+#[read_component(Immovable)]
+pub fn input_handling(
+    world: &mut SubWorld,
+    #[resource] key_pressed_events: &mut KeyPressedEventQueue,
+    #[resource] gameplay_event_queue: &mut GamePlayEventQueue,
+    #[resource] game_play: &mut GamePlay,
+    #[resource] move_history: &mut MoveHistory,
+) {
+    let mut player_query = <(Entity, &Position)>::query().filter(component::<Player>());
+    let mut movables_query = <(Entity, &Position)>::query().filter(component::<Movable>());
+    let mut immovables_query = <(Entity, &Position)>::query().filter(component::<Immovable>());
+    let mut boxes_query = <(Entity, &Box)>::query();
+    let mut box_spots_query = <(&Position, &BoxSpot)>::query();
+
+    for key in key_pressed_events.queue.drain(..) {
+        let (player_entity, player_position) =
+            if let Some((entity, position)) = player_query.iter(world).next() {
+                (*entity, *position)
+            } else {
+                continue;
+            };
+
+        let (dx, dy): (i8, i8) = match key {
+            KeyCode::Up => (0, -1),
+            KeyCode::Down => (0, 1),
+            KeyCode::Left => (-1, 0),
+            KeyCode::Right => (1, 0),
+            _ => continue,
+        };
+
+        // Scan the line of tiles ahead of the player, collecting every
+        // consecutive `Movable` entity until we hit either empty floor
+        // (the chain can shift), an `Immovable`, or the top or left edge of
+        // the grid (both cancel the whole move, the same as running into a
+        // wall).
+        let mut chain: Vec<(Entity, Position)> = Vec::new();
+        let mut probe = player_position;
+        let blocked = loop {
+            probe = match step(probe, dx, dy) {
+                Some(position) => position,
+                None => break true,
+            };
+            if immovables_query
+                .iter(world)
+                .any(|(_, position)| *position == probe)
+            {
+                break true;
+            }
+            match movables_query
+                .iter(world)
+                .find(|(_, position)| **position == probe)
+            {
+                Some((entity, position)) => {
+                    chain.push((*entity, *position));
+                }
+                None => break false,
+            }
+        };
+
+        if blocked {
+            gameplay_event_queue
+                .queue
+                .push(GameplayEvent::PlayerHitObstacle);
+            continue;
+        }
+
+        let mut record = MoveRecord {
+            changes: vec![(player_entity, player_position)],
+        };
+
+        // Shift the chain starting from its far end so no entity
+        // overwrites a cell before its occupant has moved out of it. The
+        // scan above already proved every one of these steps lands
+        // in-bounds, so the cast in `step` can't wrap here.
+        for (entity, position) in chain.iter().rev() {
+            record.changes.push((*entity, *position));
+            let new_position = step(*position, dx, dy).expect("chain scan proved this step is in-bounds");
+            if let Ok(entry) = world.entry_mut(*entity) {
+                if let Ok(position) = entry.into_component_mut::<Position>() {
+                    *position = new_position;
+                }
+            }
+        }
+
+        if let Ok(entry) = world.entry_mut(player_entity) {
+            if let Ok(position) = entry.into_component_mut::<Position>() {
+                *position = step(player_position, dx, dy).expect("chain scan proved this step is in-bounds");
+            }
+        }
+
+        let box_entities: Vec<Entity> = boxes_query.iter(world).map(|(entity, _)| *entity).collect();
+        for (entity, position) in chain.iter() {
+            if !box_entities.contains(entity) {
+                continue;
+            }
+            let target = step(*position, dx, dy).expect("chain scan proved this step is in-bounds");
+            let on_spot = box_spots_query
+                .iter(world)
+                .any(|(position, _)| position.x == target.x && position.y == target.y);
+            gameplay_event_queue
+                .queue
+                .push(GameplayEvent::BoxPlacedOnSpot(on_spot));
+        }
+
+        move_history.records.push(record);
+        game_play.moves_count += 1;
+    }
+}
+
+/// Steps `position` by `(dx, dy)`, or `None` if that would take it off the
+/// grid (below zero on either axis). Callers treat this the same as
+/// running into an `Immovable`: the move is cancelled rather than wrapping
+/// the `u8` coordinate around.
+fn step(position: Position, dx: i8, dy: i8) -> Option<Position> {
+    let x = position.x as i8 + dx;
+    let y = position.y as i8 + dy;
+    if x < 0 || y < 0 {
+        return None;
+    }
+    Some(Position {
+        x: x as u8,
+        y: y as u8,
+        z: position.z,
+    })
+}
+
+/// Pops the most recent recorded move and restores the positions it
+/// displaced, undoing it. Does nothing if there is no history (e.g. at
+/// the start of a level).
+///
+/// Always puts `game_play` back into `Playing`: undoing a move can never
+/// land on a newly-solved board, so a `Won` state left over from the move
+/// being undone is necessarily stale.
+pub fn undo_last_move(world: &mut legion::World, move_history: &mut MoveHistory, game_play: &mut GamePlay) {
+    let record = match move_history.records.pop() {
+        Some(record) => record,
+        None => return,
+    };
+    for (entity, position) in record.changes {
+        if let Some(mut entry) = world.entry(entity) {
+            if let Ok(current) = entry.get_component_mut::<Position>() {
+                *current = position;
+            }
+        }
+    }
+    game_play.moves_count = game_play.moves_count.saturating_sub(1);
+    game_play.state = GameplayState::Playing;
+}
+
+#[system]
+#[read_component(Position)]
+#[read_component(Box)]
+#[read_component(BoxSpot)]
+pub fn game_objective(world: &mut SubWorld, #[resource] game_play: &mut GamePlay) {
+    let mut boxes_query = <(&Position, &Box)>::query();
+    let mut box_spots_query = <(&Position, &BoxSpot)>::query();
+
+    let boxes: Vec<_> = boxes_query.iter(world).collect();
+    let all_boxes_on_matching_spots = box_spots_query.iter(world).all(|(spot_position, spot)| {
+        boxes.iter().any(|(box_position, box_)| {
+            box_position.x == spot_position.x
+                && box_position.y == spot_position.y
+                && matches_box_colour(&box_.colour, &spot.colour)
+        })
+    });
+
+    if all_boxes_on_matching_spots && !boxes.is_empty() {
+        game_play.state = GameplayState::Won;
+    }
+}
+
+const SFX_FADE_IN: Duration = Duration::from_millis(20);
+
+#[system]
+pub fn consume_gameplay_events(
+    #[resource] gameplay_event_queue: &mut GamePlayEventQueue,
+    #[resource] audio_store: &mut AudioStore,
+) {
+    for event in gameplay_event_queue.queue.drain(..) {
+        let sound_path = match event {
+            GameplayEvent::PlayerHitObstacle => "/sounds/wall.wav",
+            GameplayEvent::BoxPlacedOnSpot(true) => "/sounds/correct.wav",
+            GameplayEvent::BoxPlacedOnSpot(false) => "/sounds/incorrect.wav",
+        };
+        let _ = audio_store.play_sfx_varied(sound_path, Some(SFX_FADE_IN));
+    }
+}
+
+/// How much of the map grid, scaled to fit, plus its centering offset
+/// within the current window takes up.
+struct Viewport {
+    scale: f32,
+    offset: na::Point2<f32>,
+}
+
+fn viewport(ctx: &Context, map_dimensions: &MapDimensions) -> Viewport {
+    let (window_width, window_height) = graphics::drawable_size(ctx);
+    let grid_width = map_dimensions.width as f32 * TILE_WIDTH;
+    let grid_height = map_dimensions.height as f32 * TILE_HEIGHT;
+    let scale = (window_width / grid_width).min(window_height / grid_height);
+    let offset = na::Point2::new(
+        (window_width - grid_width * scale) / 2.0,
+        (window_height - grid_height * scale) / 2.0,
+    );
+    Viewport { scale, offset }
+}
+
+pub fn render_entities(
+    ctx: &mut Context,
+    world: &legion::World,
+    resources: &legion::Resources,
+) -> GameResult {
+    let drawable_store = resources.get::<DrawableStore>().unwrap();
+    let map_dimensions = resources.get::<MapDimensions>().unwrap();
+    let viewport = viewport(ctx, &map_dimensions);
+
+    let mut query = <(&Position, &Renderable)>::query();
+    let mut entities: Vec<_> = query.iter(world).collect();
+    entities.sort_by_key(|(position, _)| position.z);
+
+    for (position, renderable) in entities {
+        let image = drawable_store.image(renderable.path());
+        let draw_params = DrawParam::new()
+            .dest(na::Point2::new(
+                viewport.offset.x + position.x as f32 * TILE_WIDTH * viewport.scale,
+                viewport.offset.y + position.y as f32 * TILE_HEIGHT * viewport.scale,
+            ))
+            .scale(na::Vector2::new(viewport.scale, viewport.scale));
+        graphics::draw(ctx, image, draw_params)?;
+    }
+    Ok(())
+}
+
+pub fn render_gameplay_data(ctx: &mut Context, resources: &legion::Resources) -> GameResult {
+    let game_play = resources.get::<GamePlay>().unwrap();
+    let text = Text::new(format!("Moves: {}", game_play.moves_count));
+    graphics::draw(ctx, &text, DrawParam::new().dest(na::Point2::new(10.0, 10.0)))?;
+
+    if game_play.state == GameplayState::Won {
+        let map_dimensions = resources.get::<MapDimensions>().unwrap();
+        let viewport = viewport(ctx, &map_dimensions);
+        let won_text = Text::new("Level complete!");
+        let (text_width, text_height) = won_text.dimensions(ctx);
+        let grid_width = map_dimensions.width as f32 * TILE_WIDTH * viewport.scale;
+        let grid_height = map_dimensions.height as f32 * TILE_HEIGHT * viewport.scale;
+        graphics::draw(
+            ctx,
+            &won_text,
+            DrawParam::new().dest(na::Point2::new(
+                viewport.offset.x + (grid_width - text_width as f32) / 2.0,
+                viewport.offset.y + (grid_height - text_height as f32) / 2.0,
+            )),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_resources() -> legion::Resources {
+        let mut resources = legion::Resources::default();
+        resources.insert(GamePlay::default());
+        resources.insert(KeyPressedEventQueue::default());
+        resources.insert(GamePlayEventQueue::default());
+        resources.insert(MoveHistory::default());
+        resources
+    }
+
+    fn run_input_handling(world: &mut legion::World, resources: &mut legion::Resources, key: KeyCode) {
+        resources
+            .get_mut::<KeyPressedEventQueue>()
+            .unwrap()
+            .queue
+            .push(key);
+        let mut schedule = legion::Schedule::builder()
+            .add_system(input_handling_system())
+            .build();
+        schedule.execute(world, resources);
+    }
+
+    fn position_of(world: &legion::World, entity: Entity) -> Position {
+        *world.entry_ref(entity).unwrap().get_component::<Position>().unwrap()
+    }
+
+    #[test]
+    fn push_into_wall_is_cancelled() {
+        let mut world = legion::World::default();
+        let mut resources = new_resources();
+
+        let player = world.push((Position { x: 1, y: 1, z: 0 }, Player {}, Movable {}));
+        world.push((Position { x: 0, y: 1, z: 0 }, Immovable {}));
+
+        run_input_handling(&mut world, &mut resources, KeyCode::Left);
+
+        assert_eq!(position_of(&world, player), Position { x: 1, y: 1, z: 0 });
+        assert!(matches!(
+            resources.get::<GamePlayEventQueue>().unwrap().queue[..],
+            [GameplayEvent::PlayerHitObstacle]
+        ));
+    }
+
+    #[test]
+    fn two_box_chain_shifts_together() {
+        let mut world = legion::World::default();
+        let mut resources = new_resources();
+
+        let player = world.push((Position { x: 0, y: 1, z: 0 }, Player {}, Movable {}));
+        let box_a = world.push((
+            Position { x: 1, y: 1, z: 0 },
+            Box { colour: BoxColour::Red },
+            Movable {},
+        ));
+        let box_b = world.push((
+            Position { x: 2, y: 1, z: 0 },
+            Box { colour: BoxColour::Red },
+            Movable {},
+        ));
+
+        run_input_handling(&mut world, &mut resources, KeyCode::Right);
+
+        assert_eq!(position_of(&world, player), Position { x: 1, y: 1, z: 0 });
+        assert_eq!(position_of(&world, box_a), Position { x: 2, y: 1, z: 0 });
+        assert_eq!(position_of(&world, box_b), Position { x: 3, y: 1, z: 0 });
+    }
+
+    #[test]
+    fn push_off_grid_edge_is_cancelled() {
+        let mut world = legion::World::default();
+        let mut resources = new_resources();
+
+        let player = world.push((Position { x: 0, y: 0, z: 0 }, Player {}, Movable {}));
+
+        run_input_handling(&mut world, &mut resources, KeyCode::Up);
+
+        assert_eq!(position_of(&world, player), Position { x: 0, y: 0, z: 0 });
+        assert!(matches!(
+            resources.get::<GamePlayEventQueue>().unwrap().queue[..],
+            [GameplayEvent::PlayerHitObstacle]
+        ));
+    }
+
+    #[test]
+    fn undo_after_win_returns_to_playing() {
+        let mut world = legion::World::default();
+        let box_entity = world.push((Position { x: 2, y: 1, z: 0 },));
+        let mut move_history = MoveHistory {
+            records: vec![MoveRecord {
+                changes: vec![(box_entity, Position { x: 1, y: 1, z: 0 })],
+            }],
+        };
+        let mut game_play = GamePlay {
+            state: GameplayState::Won,
+            moves_count: 1,
+        };
+
+        undo_last_move(&mut world, &mut move_history, &mut game_play);
+
+        assert_eq!(position_of(&world, box_entity), Position { x: 1, y: 1, z: 0 });
+        assert_eq!(game_play.moves_count, 0);
+        assert_eq!(game_play.state, GameplayState::Playing);
+        assert!(move_history.records.is_empty());
+    }
+}